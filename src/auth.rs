@@ -0,0 +1,132 @@
+use diesel::backend::Backend;
+use diesel::expression::BoxableExpression;
+use diesel::helper_types::{Filter, Select};
+use diesel::sql_types::{Bool, Text};
+use diesel::serialize::ToSql;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+
+use crate::query::{certifiers, entities, signatures, EntityStatus};
+
+/// Identifies who is asking, so `modify_query` can decide what they may see.
+/// Anonymous callers (`None`) get the most restrictive view.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ViewerIdentity {
+    Entity(String),
+    Key(i32),
+}
+
+/// A set of predicates a [`crate::query::MyContext`] applies while loading
+/// `entities`, expressed independently of any single viewer so callers can
+/// plug in their own authorization rules instead of [`DefaultVisibilityPolicy`].
+pub trait VisibilityPolicy<DB>
+where
+    DB: Backend,
+{
+    /// Restricts the `entities` table to the rows `viewer` is allowed to see.
+    fn entities_filter(
+        &self,
+        viewer: Option<&ViewerIdentity>,
+    ) -> Box<dyn BoxableExpression<entities::table, DB, SqlType = Bool>>;
+}
+
+/// Hides `DRAFT` entities from anonymous callers, and from
+/// `ViewerIdentity::Entity` viewers that neither signed nor certify them.
+/// A `ViewerIdentity::Key` viewer gets the same view as an anonymous
+/// caller; signatures made with that key are not taken into account.
+/// `CURRENT`/`OLD` revisions are always visible.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultVisibilityPolicy;
+
+impl<DB> VisibilityPolicy<DB> for DefaultVisibilityPolicy
+where
+    DB: Backend + 'static,
+    String: ToSql<Text, DB>,
+    EntityStatus: ToSql<Text, DB>,
+{
+    fn entities_filter(
+        &self,
+        viewer: Option<&ViewerIdentity>,
+    ) -> Box<dyn BoxableExpression<entities::table, DB, SqlType = Bool>> {
+        let not_draft = entities::status.ne(EntityStatus::DRAFT);
+        match viewer {
+            None => Box::new(not_draft),
+            Some(ViewerIdentity::Entity(hash)) => {
+                let signed_by_viewer = entities::hash.eq_any(
+                    signatures::table
+                        .filter(signatures::by.eq(hash.clone()))
+                        .select(signatures::hash),
+                );
+                let certified_by_viewer = entities::hash.eq_any(
+                    certifiers::table
+                        .filter(certifiers::certifier.eq(hash.clone()))
+                        .select(certifiers::entity),
+                );
+                Box::new(not_draft.or(signed_by_viewer).or(certified_by_viewer))
+            }
+            Some(ViewerIdentity::Key(_)) => Box::new(not_draft),
+        }
+    }
+}
+
+/// The subquery selecting the `entities::hash` values `viewer` may see
+/// under [`DefaultVisibilityPolicy`]. `signatures` and `certifiers` are
+/// query roots in their own right (see `wundergraph::query_object!` in
+/// `crate::query`), so a caller can read them directly instead of through
+/// `Entity`; any column on those tables that points back at an entity
+/// must also be constrained to this set, or the entity-visibility policy
+/// is a side door away from being bypassed entirely.
+pub fn visible_entity_hashes<DB>(
+    viewer: Option<&ViewerIdentity>,
+) -> Select<Filter<entities::table, Box<dyn BoxableExpression<entities::table, DB, SqlType = Bool>>>, entities::hash>
+where
+    DB: Backend + 'static,
+    DefaultVisibilityPolicy: VisibilityPolicy<DB>,
+{
+    entities::table
+        .filter(DefaultVisibilityPolicy.entities_filter(viewer))
+        .select(entities::hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+    use diesel::sqlite::Sqlite;
+
+    #[test]
+    fn anonymous_viewer_excludes_draft_only() {
+        let filter = DefaultVisibilityPolicy.entities_filter(None);
+        let sql = debug_query::<Sqlite, _>(&entities::table.filter(filter)).to_string();
+
+        assert!(sql.contains("`entities`.`status`"));
+        assert!(!sql.contains("signatures"));
+        assert!(!sql.contains("certifiers"));
+    }
+
+    #[test]
+    fn entity_viewer_also_sees_what_they_signed_or_certified() {
+        let viewer = ViewerIdentity::Entity("viewer-hash".to_string());
+        let filter = DefaultVisibilityPolicy.entities_filter(Some(&viewer));
+        let sql = debug_query::<Sqlite, _>(&entities::table.filter(filter)).to_string();
+
+        assert!(sql.contains("`signatures`"));
+        assert!(sql.contains("`certifiers`"));
+    }
+
+    #[test]
+    fn key_viewer_gets_the_same_filter_as_anonymous() {
+        let viewer = ViewerIdentity::Key(42);
+        let filter = DefaultVisibilityPolicy.entities_filter(Some(&viewer));
+        let sql = debug_query::<Sqlite, _>(&entities::table.filter(filter)).to_string();
+
+        assert!(!sql.contains("signatures"));
+        assert!(!sql.contains("certifiers"));
+    }
+
+    #[test]
+    fn visible_entity_hashes_selects_only_the_hash_column() {
+        let sql = debug_query::<Sqlite, _>(&visible_entity_hashes::<Sqlite>(None)).to_string();
+
+        assert!(sql.contains("SELECT `entities`.`hash`"));
+    }
+}
@@ -0,0 +1,127 @@
+use diesel::connection::SimpleConnection;
+use diesel::r2d2;
+use std::time::Duration;
+
+use crate::query::DBConnection;
+
+/// Tunes PRAGMAs on every SQLite connection handed out by the pool.
+///
+/// `entities`, `keys`, `signatures` and `certifiers` rely on foreign keys
+/// for referential integrity, but SQLite leaves them disabled unless a
+/// connection opts in, and the default busy behavior is to fail fast
+/// instead of waiting out a writer holding the WAL lock.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionOptions {
+    pub enable_foreign_keys: bool,
+    pub busy_timeout: Option<Duration>,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            enable_foreign_keys: true,
+            busy_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl r2d2::CustomizeConnection<DBConnection, r2d2::Error> for ConnectionOptions {
+    // Mirrors the `DBConnection` selection in `crate::query`: SQLite is
+    // the backend whenever the `sqlite` feature is on, or as the default
+    // when neither backend feature is selected.
+    #[cfg(any(feature = "sqlite", not(feature = "postgres")))]
+    fn on_acquire(&self, conn: &mut DBConnection) -> Result<(), r2d2::Error> {
+        let mut pragmas = String::new();
+        if self.enable_foreign_keys {
+            pragmas.push_str("PRAGMA foreign_keys = ON;");
+        }
+        if let Some(timeout) = self.busy_timeout {
+            pragmas.push_str(&format!("PRAGMA busy_timeout = {};", timeout.as_millis()));
+        }
+        conn.batch_execute(&pragmas)
+            .map_err(|error| r2d2::Error::QueryError(error))
+    }
+
+    // `enable_foreign_keys`/`busy_timeout` are SQLite PRAGMAs; Postgres
+    // enforces foreign keys unconditionally and exposes lock waiting via
+    // `statement_timeout`/`lock_timeout`, not a connection PRAGMA, so
+    // there is nothing backend-appropriate to run here yet.
+    #[cfg(all(feature = "postgres", not(feature = "sqlite")))]
+    fn on_acquire(&self, _conn: &mut DBConnection) -> Result<(), r2d2::Error> {
+        Ok(())
+    }
+}
+
+/// Builds the connection pool with [`ConnectionOptions`] attached so every
+/// checked-out connection honors the referential integrity the schema
+/// already declares.
+pub fn build_pool(
+    database_url: impl Into<String>,
+    options: ConnectionOptions,
+) -> Result<r2d2::Pool<r2d2::ConnectionManager<DBConnection>>, r2d2::PoolError> {
+    let manager = r2d2::ConnectionManager::<DBConnection>::new(database_url.into());
+    r2d2::Pool::builder()
+        .connection_customizer(Box::new(options))
+        .build(manager)
+}
+
+/// Tunables for [`connect_with_retry`]'s exponential backoff.
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffConfig {
+    pub initial_interval: Duration,
+    pub multiplier: f64,
+    pub max_elapsed_time: Duration,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(500),
+            multiplier: 1.5,
+            max_elapsed_time: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Returns `true` for pool errors that are worth retrying: the backing
+/// store not being up yet, or SQLite reporting a transient lock.
+fn is_transient(error: &r2d2::PoolError) -> bool {
+    let message = error.to_string().to_lowercase();
+    message.contains("database is locked")
+        || message.contains("unable to open")
+        || message.contains("connection refused")
+        || message.contains("connection reset")
+        || message.contains("connection aborted")
+}
+
+/// Connects to the database, retrying with exponential backoff while the
+/// backing store is still coming up (common when the GraphQL service
+/// starts before its database in containerized deploys). Authentication
+/// or schema errors are not transient and are returned immediately.
+pub fn connect_with_retry(
+    database_url: impl Into<String>,
+    options: ConnectionOptions,
+    backoff_config: BackoffConfig,
+) -> Result<r2d2::Pool<r2d2::ConnectionManager<DBConnection>>, r2d2::PoolError> {
+    let database_url = database_url.into();
+    let mut backoff = backoff::ExponentialBackoff {
+        current_interval: backoff_config.initial_interval,
+        initial_interval: backoff_config.initial_interval,
+        multiplier: backoff_config.multiplier,
+        max_elapsed_time: Some(backoff_config.max_elapsed_time),
+        ..backoff::ExponentialBackoff::default()
+    };
+
+    backoff::retry(&mut backoff, || {
+        build_pool(database_url.clone(), options).map_err(|error| {
+            if is_transient(&error) {
+                backoff::Error::Transient(error)
+            } else {
+                backoff::Error::Permanent(error)
+            }
+        })
+    })
+    .map_err(|error| match error {
+        backoff::Error::Transient(error) | backoff::Error::Permanent(error) => error,
+    })
+}
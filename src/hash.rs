@@ -0,0 +1,191 @@
+use sha2::{Digest as _, Sha256, Sha512};
+
+use crate::query::EntityStatus;
+
+/// The digest a caller may select for [`compute_entity_hash`]/
+/// [`verify_entity_hash`]. Defaults to `Sha256`, the multihash-style
+/// identifier already in use for existing rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl Default for DigestAlgorithm {
+    fn default() -> Self {
+        DigestAlgorithm::Sha256
+    }
+}
+
+impl DigestAlgorithm {
+    /// The multihash-style name prefixed to the hex digest.
+    fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha2-256",
+            DigestAlgorithm::Sha512 => "sha2-512",
+        }
+    }
+
+    fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha256 => Sha256::digest(bytes).to_vec(),
+            DigestAlgorithm::Sha512 => Sha512::digest(bytes).to_vec(),
+        }
+    }
+}
+
+/// Writes a length-prefixed UTF-8 string so the canonical encoding can't
+/// be confused by a value that happens to contain a field separator.
+fn write_field(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u64).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// Canonical byte encoding of an entity's mutable fields, in a stable
+/// field order, used as the preimage for its content-addressed hash.
+fn canonical_bytes(
+    parent: &str,
+    revision: i32,
+    name: &str,
+    info: Option<&str>,
+    status: EntityStatus,
+) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_field(&mut buf, parent);
+    buf.extend_from_slice(&revision.to_be_bytes());
+    write_field(&mut buf, name);
+    buf.push(info.is_some() as u8);
+    write_field(&mut buf, info.unwrap_or(""));
+    write_field(&mut buf, status.to_str());
+    buf
+}
+
+/// Renders a digest as a multihash-style string: `<digest-name>-<hex>`.
+fn multihash(digest_name: &str, digest: &[u8]) -> String {
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    format!("{}-{}", digest_name, hex)
+}
+
+/// Recomputes the content address for an entity's mutable fields using
+/// `digest`. This is what `Entity::hash` must equal for the row to be
+/// considered authentic.
+pub fn compute_entity_hash(
+    digest: DigestAlgorithm,
+    parent: &str,
+    revision: i32,
+    name: &str,
+    info: Option<&str>,
+    status: EntityStatus,
+) -> String {
+    let bytes = canonical_bytes(parent, revision, name, info, status);
+    multihash(digest.name(), &digest.digest(&bytes))
+}
+
+/// Checks that `hash` is the content address of the given mutable fields
+/// under `digest`.
+pub fn verify_entity_hash(
+    digest: DigestAlgorithm,
+    hash: &str,
+    parent: &str,
+    revision: i32,
+    name: &str,
+    info: Option<&str>,
+    status: EntityStatus,
+) -> bool {
+    compute_entity_hash(digest, parent, revision, name, info, status) == hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_then_verify_round_trips() {
+        let hash = compute_entity_hash(
+            DigestAlgorithm::Sha256,
+            "parent-hash",
+            1,
+            "radicle",
+            Some("a project"),
+            EntityStatus::CURRENT,
+        );
+        assert!(hash.starts_with("sha2-256-"));
+        assert!(verify_entity_hash(
+            DigestAlgorithm::Sha256,
+            &hash,
+            "parent-hash",
+            1,
+            "radicle",
+            Some("a project"),
+            EntityStatus::CURRENT,
+        ));
+    }
+
+    #[test]
+    fn verify_rejects_tampered_field() {
+        let hash = compute_entity_hash(
+            DigestAlgorithm::Sha256,
+            "parent-hash",
+            1,
+            "radicle",
+            None,
+            EntityStatus::CURRENT,
+        );
+        assert!(!verify_entity_hash(
+            DigestAlgorithm::Sha256,
+            &hash,
+            "parent-hash",
+            2, // revision tampered with after hashing
+            "radicle",
+            None,
+            EntityStatus::CURRENT,
+        ));
+    }
+
+    #[test]
+    fn different_digests_disagree() {
+        let sha256 = compute_entity_hash(
+            DigestAlgorithm::Sha256,
+            "parent-hash",
+            1,
+            "radicle",
+            None,
+            EntityStatus::CURRENT,
+        );
+        let sha512 = compute_entity_hash(
+            DigestAlgorithm::Sha512,
+            "parent-hash",
+            1,
+            "radicle",
+            None,
+            EntityStatus::CURRENT,
+        );
+        assert!(sha256.starts_with("sha2-256-"));
+        assert!(sha512.starts_with("sha2-512-"));
+        assert_ne!(sha256, sha512);
+    }
+
+    #[test]
+    fn missing_info_and_empty_info_hash_differently() {
+        let without_info = compute_entity_hash(
+            DigestAlgorithm::Sha256,
+            "parent-hash",
+            1,
+            "radicle",
+            None,
+            EntityStatus::CURRENT,
+        );
+        let with_empty_info = compute_entity_hash(
+            DigestAlgorithm::Sha256,
+            "parent-hash",
+            1,
+            "radicle",
+            Some(""),
+            EntityStatus::CURRENT,
+        );
+        assert_ne!(without_info, with_empty_info);
+    }
+}
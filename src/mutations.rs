@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fmt;
+
+use diesel::{Insertable, RunQueryDsl};
+
+use crate::hash::{compute_entity_hash, DigestAlgorithm};
+use crate::query::{entities, DBConnection, EntityStatus};
+
+/// Raised when a caller tries to insert or update an entity whose `hash`
+/// does not match the content address of its mutable fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMismatch {
+    pub claimed: String,
+    pub expected: String,
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "entity hash `{}` does not match its content address `{}`",
+            self.claimed, self.expected
+        )
+    }
+}
+
+impl Error for HashMismatch {}
+
+/// Guards entity mutations against tampered or mislabeled content
+/// addresses: recomputes the hash (with `digest`) from the mutable fields
+/// and rejects the mutation if it doesn't match what the caller claims,
+/// mirroring the content-addressing invariant the schema already keys
+/// rows by.
+pub fn guard_entity_hash(
+    digest: DigestAlgorithm,
+    claimed_hash: &str,
+    parent: &str,
+    revision: i32,
+    name: &str,
+    info: Option<&str>,
+    status: EntityStatus,
+) -> Result<(), HashMismatch> {
+    let expected = compute_entity_hash(digest, parent, revision, name, info, status);
+    if expected == claimed_hash {
+        Ok(())
+    } else {
+        Err(HashMismatch {
+            claimed: claimed_hash.to_owned(),
+            expected,
+        })
+    }
+}
+
+/// The row `insert_entity` writes once [`guard_entity_hash`] has passed.
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "entities"]
+pub struct NewEntity {
+    pub hash: String,
+    pub parent: String,
+    pub revision: i32,
+    pub timestamp: chrono::naive::NaiveDateTime,
+    pub status: EntityStatus,
+    pub name: String,
+    pub info: Option<String>,
+}
+
+/// Why an entity mutation was rejected.
+#[derive(Debug)]
+pub enum InsertEntityError {
+    HashMismatch(HashMismatch),
+    Query(diesel::result::Error),
+}
+
+impl fmt::Display for InsertEntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertEntityError::HashMismatch(error) => error.fmt(f),
+            InsertEntityError::Query(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for InsertEntityError {}
+
+impl From<HashMismatch> for InsertEntityError {
+    fn from(error: HashMismatch) -> Self {
+        InsertEntityError::HashMismatch(error)
+    }
+}
+
+/// Inserts a new entity row, guarded by [`guard_entity_hash`] (using
+/// `digest`) so a tampered or mislabeled content address is rejected
+/// before it ever reaches the database.
+pub fn insert_entity(
+    conn: &DBConnection,
+    digest: DigestAlgorithm,
+    new_entity: NewEntity,
+) -> Result<usize, InsertEntityError> {
+    guard_entity_hash(
+        digest,
+        &new_entity.hash,
+        &new_entity.parent,
+        new_entity.revision,
+        &new_entity.name,
+        new_entity.info.as_deref(),
+        new_entity.status,
+    )?;
+
+    diesel::insert_into(entities::table)
+        .values(&new_entity)
+        .execute(conn)
+        .map_err(InsertEntityError::Query)
+}
@@ -34,13 +34,16 @@ use diesel::deserialize::{self, FromSql};
 use diesel::r2d2::{ConnectionManager, PooledConnection};
 use diesel::serialize::{self, ToSql};
 use diesel::sql_types::Text;
-use diesel::{AsExpression, Connection, FromSqlRow, Identifiable};
+use diesel::{
+    AsExpression, BoolExpressionMethods, Connection, ExpressionMethods, FromSqlRow, Identifiable,
+    NullableExpressionMethods, QueryDsl,
+};
 use juniper::LookAheadSelection;
 use std::io::Write;
 use wundergraph::error::Result;
 use wundergraph::query_builder::selection::offset::ApplyOffset;
 use wundergraph::query_builder::selection::{BoxedQuery, LoadingHandler, QueryModifier};
-use wundergraph::query_builder::types::{HasOne, WundergraphValue};
+use wundergraph::query_builder::types::{HasMany, HasOne, WundergraphValue};
 use wundergraph::scalar::WundergraphScalarValue;
 use wundergraph::WundergraphContext;
 use wundergraph::WundergraphEntity;
@@ -48,6 +51,9 @@ use wundergraph::WundergraphEntity;
 use diesel::table;
 use juniper::GraphQLEnum;
 
+use crate::auth::{
+    visible_entity_hashes, DefaultVisibilityPolicy, VisibilityPolicy, ViewerIdentity,
+};
 use crate::mutations::*;
 
 #[derive(
@@ -55,23 +61,23 @@ use crate::mutations::*;
 )]
 #[sql_type = "Text"]
 pub enum KeyAlgo {
-    FOO,
-    BAR,
+    ED25519,
+    SECP256K1,
 }
 
 impl KeyAlgo {
     pub fn from_str(text: &str) -> Option<Self> {
         match text {
-            "FOO" => Some(KeyAlgo::FOO),
-            "BAR" => Some(KeyAlgo::BAR),
+            "ED25519" => Some(KeyAlgo::ED25519),
+            "SECP256K1" => Some(KeyAlgo::SECP256K1),
             _ => None,
         }
     }
 
     pub fn to_str(&self) -> &'static str {
         match self {
-            KeyAlgo::FOO => "FOO",
-            KeyAlgo::BAR => "BAR",
+            KeyAlgo::ED25519 => "ED25519",
+            KeyAlgo::SECP256K1 => "SECP256K1",
         }
     }
 }
@@ -207,8 +213,12 @@ pub struct Entity {
     status: EntityStatus,
     name: String,
     info: Option<String>,
-    //keys: HasMany<Key, keys::id>,
-    //signatures: HasMany<Signature, signatures::key>,
+    // No column in `keys` references `entities`, so "keys belonging to
+    // this entity" can't be a direct wundergraph `HasMany` field; reach
+    // them by walking `signatures { key { ... } }` instead.
+    signatures: HasMany<Signature, signatures::hash>,
+    certifiers: HasMany<Certifier, certifiers::entity>,
+    certifies: HasMany<Certifier, certifiers::certifier>,
 }
 
 #[derive(Clone, Debug, Queryable, Eq, PartialEq, Hash, WundergraphEntity, Identifiable)]
@@ -246,6 +256,84 @@ pub struct Certifier {
     entity: HasOne<String, Entity>,
 }
 
+impl Entity {
+    pub(crate) fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub(crate) fn parent(&self) -> &str {
+        &self.parent
+    }
+
+    pub(crate) fn revision(&self) -> i32 {
+        self.revision
+    }
+
+    pub(crate) fn timestamp(&self) -> chrono::naive::NaiveDateTime {
+        self.timestamp
+    }
+
+    pub(crate) fn status(&self) -> EntityStatus {
+        self.status
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn info(&self) -> Option<&str> {
+        self.info.as_deref()
+    }
+}
+
+impl Key {
+    pub(crate) fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub(crate) fn algo(&self) -> KeyAlgo {
+        self.algo
+    }
+}
+
+impl Device {
+    pub(crate) fn key_id(&self) -> i32 {
+        *self.key.id()
+    }
+
+    pub(crate) fn address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+}
+
+impl Signature {
+    pub(crate) fn key_id(&self) -> i32 {
+        *self.key.id()
+    }
+
+    pub(crate) fn entity_hash(&self) -> &str {
+        self.hash.id()
+    }
+
+    pub(crate) fn data(&self) -> &str {
+        &self.data
+    }
+
+    pub(crate) fn by(&self) -> Option<&str> {
+        self.by.as_ref().map(|by| by.id().as_str())
+    }
+}
+
+impl Certifier {
+    pub(crate) fn certifier_hash(&self) -> &str {
+        self.certifier.id()
+    }
+
+    pub(crate) fn entity_hash(&self) -> &str {
+        self.entity.id()
+    }
+}
+
 wundergraph::query_object! {
     /// Global query object for the schema
     Query {
@@ -263,6 +351,7 @@ where
     Conn: Connection + 'static,
 {
     conn: PooledConnection<ConnectionManager<Conn>>,
+    viewer: Option<ViewerIdentity>,
 }
 
 impl<Conn> MyContext<Conn>
@@ -270,42 +359,144 @@ where
     Conn: Connection + 'static,
 {
     pub fn new(conn: PooledConnection<ConnectionManager<Conn>>) -> Self {
-        Self { conn }
+        Self { conn, viewer: None }
+    }
+
+    pub fn with_viewer(
+        conn: PooledConnection<ConnectionManager<Conn>>,
+        viewer: ViewerIdentity,
+    ) -> Self {
+        Self {
+            conn,
+            viewer: Some(viewer),
+        }
     }
 }
 
-impl<T, C, DB> QueryModifier<T, DB> for MyContext<C>
+impl<C, DB> QueryModifier<Entity, DB> for MyContext<C>
 where
     C: Connection<Backend = DB>,
     DB: Backend + ApplyOffset + 'static,
-    T: LoadingHandler<DB, Self>,
+    Entity: LoadingHandler<DB, Self>,
     Self: WundergraphContext,
     Self::Connection: Connection<Backend = DB>,
+    DefaultVisibilityPolicy: VisibilityPolicy<DB>,
 {
     fn modify_query<'a>(
         &self,
         _select: &LookAheadSelection<'_, WundergraphScalarValue>,
-        query: BoxedQuery<'a, T, DB, Self>,
-    ) -> Result<BoxedQuery<'a, T, DB, Self>> {
-        match T::TYPE_NAME {
-            //            "Heros" => Err(Error::from_boxed_compat(String::from("Is user").into())),
-            _ => Ok(query),
-        }
+        query: BoxedQuery<'a, Entity, DB, Self>,
+    ) -> Result<BoxedQuery<'a, Entity, DB, Self>> {
+        let filter = DefaultVisibilityPolicy.entities_filter(self.viewer.as_ref());
+        Ok(query.filter(filter))
     }
 }
 
-impl WundergraphContext for MyContext<DBConnection> {
-    type Connection = diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<DBConnection>>;
+impl<C, DB> QueryModifier<Signature, DB> for MyContext<C>
+where
+    C: Connection<Backend = DB>,
+    DB: Backend + ApplyOffset + 'static,
+    Signature: LoadingHandler<DB, Self>,
+    Self: WundergraphContext,
+    Self::Connection: Connection<Backend = DB>,
+    DefaultVisibilityPolicy: VisibilityPolicy<DB>,
+{
+    fn modify_query<'a>(
+        &self,
+        _select: &LookAheadSelection<'_, WundergraphScalarValue>,
+        query: BoxedQuery<'a, Signature, DB, Self>,
+    ) -> Result<BoxedQuery<'a, Signature, DB, Self>> {
+        // `signatures` is a query root of its own, so it must not leak a
+        // signature's `hash` (the entity it signs) or its `by` (the signer,
+        // when present) for an entity that `Entity`'s own filtering would
+        // otherwise hide.
+        let visible = visible_entity_hashes::<DB>(self.viewer.as_ref());
+        let visible_signer = entities::table
+            .filter(DefaultVisibilityPolicy.entities_filter(self.viewer.as_ref()))
+            .select(entities::hash.nullable());
+        Ok(query.filter(
+            signatures::hash.eq_any(visible).and(
+                signatures::by
+                    .is_null()
+                    .or(signatures::by.eq_any(visible_signer)),
+            ),
+        ))
+    }
+}
+
+impl<C, DB> QueryModifier<Certifier, DB> for MyContext<C>
+where
+    C: Connection<Backend = DB>,
+    DB: Backend + ApplyOffset + 'static,
+    Certifier: LoadingHandler<DB, Self>,
+    Self: WundergraphContext,
+    Self::Connection: Connection<Backend = DB>,
+    DefaultVisibilityPolicy: VisibilityPolicy<DB>,
+{
+    fn modify_query<'a>(
+        &self,
+        _select: &LookAheadSelection<'_, WundergraphScalarValue>,
+        query: BoxedQuery<'a, Certifier, DB, Self>,
+    ) -> Result<BoxedQuery<'a, Certifier, DB, Self>> {
+        // Same reasoning as `Signature`: both the certified entity and
+        // the certifier are entity hashes that must respect the policy,
+        // or querying `certifiers` directly bypasses it.
+        let visible_entity = visible_entity_hashes::<DB>(self.viewer.as_ref());
+        let visible_certifier = visible_entity_hashes::<DB>(self.viewer.as_ref());
+        Ok(query.filter(
+            certifiers::entity
+                .eq_any(visible_entity)
+                .and(certifiers::certifier.eq_any(visible_certifier)),
+        ))
+    }
+}
+
+/// `Key`/`Device` carry no entity hash of their own, so they have no
+/// visibility rules to enforce: their rows are returned unfiltered,
+/// whether queried directly or reached through another row's `key`.
+macro_rules! passthrough_query_modifier {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl<C, DB> QueryModifier<$ty, DB> for MyContext<C>
+            where
+                C: Connection<Backend = DB>,
+                DB: Backend + ApplyOffset + 'static,
+                $ty: LoadingHandler<DB, Self>,
+                Self: WundergraphContext,
+                Self::Connection: Connection<Backend = DB>,
+            {
+                fn modify_query<'a>(
+                    &self,
+                    _select: &LookAheadSelection<'_, WundergraphScalarValue>,
+                    query: BoxedQuery<'a, $ty, DB, Self>,
+                ) -> Result<BoxedQuery<'a, $ty, DB, Self>> {
+                    Ok(query)
+                }
+            }
+        )*
+    };
+}
+
+passthrough_query_modifier!(Key, Device);
+
+impl<Conn> WundergraphContext for MyContext<Conn>
+where
+    Conn: Connection + 'static,
+{
+    type Connection = diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<Conn>>;
 
     fn get_connection(&self) -> &Self::Connection {
         &self.conn
     }
 }
 
-//#[cfg(feature = "postgres")]
-//pub type DBConnection = ::diesel::PgConnection;
+#[cfg(all(feature = "sqlite", feature = "postgres"))]
+compile_error!("features `sqlite` and `postgres` are mutually exclusive");
+
+#[cfg(feature = "postgres")]
+pub type DBConnection = ::diesel::PgConnection;
 
-//#[cfg(feature = "sqlite")]
+#[cfg(any(feature = "sqlite", not(feature = "postgres")))]
 pub type DBConnection = ::diesel::SqliteConnection;
 
 //pub type DbBackend = <DBConnection as Connection>::Backend;
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::query::{Certifier, Device, Entity, Key, Signature};
+
+const NS: &str = "https://radicle.xyz/ns#";
+const BASE_IRI: &str = "https://radicle.xyz/id/";
+const KEY_IRI: &str = "https://radicle.xyz/key/";
+
+/// The rows needed to render the certifier/signature trust graph as RDF.
+/// Borrowed rather than owned so the caller decides how the rows were
+/// loaded (a single query, a paginated scan, ...).
+#[derive(Debug, Clone, Copy)]
+pub struct GraphRows<'a> {
+    pub entities: &'a [Entity],
+    pub keys: &'a [Key],
+    pub devices: &'a [Device],
+    pub signatures: &'a [Signature],
+    pub certifiers: &'a [Certifier],
+}
+
+/// Escapes a Turtle string literal body (backslash, quote, line breaks).
+fn escape_literal(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes characters that are not legal inside a Turtle IRIREF.
+fn escape_iri(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        let needs_escaping = matches!(ch, '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' | ' ')
+            || (ch as u32) < 0x20;
+        if needs_escaping {
+            let mut buf = [0; 4];
+            for byte in ch.encode_utf8(&mut buf).as_bytes() {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            escaped.push(ch);
+        }
+    }
+    escaped
+}
+
+fn entity_iri(hash: &str) -> String {
+    format!("{}{}", BASE_IRI, escape_iri(hash))
+}
+
+fn key_iri(id: i32) -> String {
+    format!("{}{}", KEY_IRI, id)
+}
+
+/// Streams the certifier/signature trust graph as Turtle so it
+/// can be fed to external SPARQL/reasoning tooling. Writes row by row
+/// rather than buffering the whole graph.
+pub fn write_turtle<W: Write>(rows: &GraphRows<'_>, out: &mut W) -> io::Result<()> {
+    writeln!(out, "@prefix : <{}> .", NS)?;
+    writeln!(out, "@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .")?;
+    writeln!(out)?;
+
+    let keys_by_id: HashMap<i32, &Key> = rows.keys.iter().map(|key| (key.id(), key)).collect();
+    let devices_by_key: HashMap<i32, &Device> = rows
+        .devices
+        .iter()
+        .map(|device| (device.key_id(), device))
+        .collect();
+
+    for key in rows.keys {
+        let subject = key_iri(key.id());
+        writeln!(out, "<{}> :algo \"{}\" .", subject, key.algo().to_str())?;
+        if let Some(address) = devices_by_key.get(&key.id()).and_then(|device| device.address()) {
+            writeln!(out, "<{}> :address \"{}\" .", subject, escape_literal(address))?;
+        }
+    }
+
+    for entity in rows.entities {
+        let subject = entity_iri(entity.hash());
+        writeln!(out, "<{}> :revision {} ;", subject, entity.revision())?;
+        writeln!(
+            out,
+            "    :status \"{}\" ;",
+            escape_literal(entity.status().to_str())
+        )?;
+        writeln!(out, "    :name \"{}\" ;", escape_literal(entity.name()))?;
+        writeln!(
+            out,
+            "    :timestamp \"{}\"^^xsd:dateTime ;",
+            entity.timestamp().format("%Y-%m-%dT%H:%M:%S")
+        )?;
+        if let Some(info) = entity.info() {
+            writeln!(out, "    :info \"{}\" ;", escape_literal(info))?;
+        }
+        writeln!(out, "    :parent <{}> .", entity_iri(entity.parent()))?;
+    }
+
+    for certifier in rows.certifiers {
+        writeln!(
+            out,
+            "<{}> :certifies <{}> .",
+            entity_iri(certifier.certifier_hash()),
+            entity_iri(certifier.entity_hash())
+        )?;
+    }
+
+    for (index, signature) in rows.signatures.iter().enumerate() {
+        let key = keys_by_id
+            .get(&signature.key_id())
+            .map(|key| (key.id(), key.algo().to_str()));
+        write_signature_triples(
+            out,
+            index,
+            signature.entity_hash(),
+            signature.by(),
+            signature.data(),
+            signature.key_id(),
+            key,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the triples for a single `signatures` row: the optional
+/// `:signs` triple (omitted when `by` is absent) and the blank-node
+/// `:signatureOf`/`:data`/`:keyId`/`:keyAlgo` triples, which don't depend
+/// on `by` and must be written regardless.
+fn write_signature_triples<W: Write>(
+    out: &mut W,
+    index: usize,
+    entity_hash: &str,
+    by: Option<&str>,
+    data: &str,
+    key_id: i32,
+    key: Option<(i32, &str)>,
+) -> io::Result<()> {
+    let subject = entity_iri(entity_hash);
+
+    // `by` is nullable: omit just the `:signs` triple it backs when
+    // absent, the rest of the signature's triples don't depend on it.
+    if let Some(by) = by {
+        writeln!(out, "<{}> :signs <{}> .", entity_iri(by), subject)?;
+    }
+
+    let blank = format!("_:sig{}", index);
+    writeln!(out, "{} :signatureOf <{}> ;", blank, subject)?;
+    writeln!(out, "    :data \"{}\" ;", escape_literal(data))?;
+    match key {
+        Some((id, algo)) => writeln!(out, "    :keyId {} ;\n    :keyAlgo \"{}\" .", id, algo)?,
+        None => writeln!(out, "    :keyId {} .", key_id)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_literal_escapes_quotes_and_control_chars() {
+        assert_eq!(
+            escape_literal("say \"hi\"\\now\n"),
+            "say \\\"hi\\\"\\\\now\\n"
+        );
+    }
+
+    #[test]
+    fn escape_iri_percent_encodes_illegal_chars() {
+        assert_eq!(escape_iri("a b"), "a%20b");
+        assert_eq!(escape_iri("<x>"), "%3Cx%3E");
+        assert_eq!(escape_iri("plain-hash123"), "plain-hash123");
+    }
+
+    #[test]
+    fn entity_iri_escapes_the_hash() {
+        assert_eq!(
+            entity_iri("a b"),
+            "https://radicle.xyz/id/a%20b".to_string()
+        );
+    }
+
+    #[test]
+    fn signature_without_by_omits_signs_triple_only() {
+        let mut out = Vec::new();
+        write_signature_triples(&mut out, 0, "entity-hash", None, "sig-data", 7, None).unwrap();
+        let turtle = String::from_utf8(out).unwrap();
+
+        assert!(!turtle.contains(":signs"));
+        assert!(turtle.contains(":signatureOf <https://radicle.xyz/id/entity-hash>"));
+        assert!(turtle.contains(":data \"sig-data\""));
+        assert!(turtle.contains(":keyId 7"));
+    }
+
+    #[test]
+    fn signature_with_by_emits_signs_triple() {
+        let mut out = Vec::new();
+        write_signature_triples(
+            &mut out,
+            0,
+            "entity-hash",
+            Some("signer-hash"),
+            "sig-data",
+            7,
+            Some((7, "ED25519")),
+        )
+        .unwrap();
+        let turtle = String::from_utf8(out).unwrap();
+
+        assert!(turtle.contains("<https://radicle.xyz/id/signer-hash> :signs <https://radicle.xyz/id/entity-hash>"));
+        assert!(turtle.contains(":keyAlgo \"ED25519\""));
+    }
+}